@@ -1,51 +1,294 @@
 use std::{
-    collections::{hash_map::Entry, HashMap},
-    sync::Mutex,
-    time::SystemTime,
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex, RwLock,
+    },
+    time::{Duration, SystemTime},
 };
 
 use crate::attributes::AttributeSet;
-use crate::metrics::data::{self, Aggregation, DataPoint, Temporality};
-use opentelemetry::{global, metrics::MetricsError};
+use crate::metrics::data::{self, Aggregation, DataPoint, Exemplar, Temporality};
+use opentelemetry::trace::TraceContextExt;
+use opentelemetry::{global, metrics::MetricsError, Context, KeyValue};
+
+use super::{aggregate::STREAM_OVERFLOW_ATTRIBUTE_SET, AtomicTracker, AtomicallyUpdate, Number};
+
+/// Caps the number of distinct attribute-set streams an aggregation tracks,
+/// folding any measurement beyond the limit into a shared overflow series.
+///
+/// This is constructed once per `Sum`/`PrecomputedSum` from the stream's
+/// configured cardinality limit (e.g. a view's `AggregationCardinalityLimit`)
+/// rather than a single process-wide constant, so different instruments can
+/// tolerate different amounts of attribute fan-out. A `max_cardinality` of
+/// `0` means unlimited.
+pub(crate) struct Limiter {
+    max_cardinality: usize,
+}
 
-use super::{
-    aggregate::{is_under_cardinality_limit, STREAM_OVERFLOW_ATTRIBUTE_SET},
-    Number,
-};
+impl Limiter {
+    pub(crate) fn new(max_cardinality: usize) -> Self {
+        Limiter { max_cardinality }
+    }
+
+    /// Returns the key a measurement with `size` existing streams should be
+    /// recorded under: `attrs` itself while there's room, or the shared
+    /// overflow attribute set once the limit is reached. One slot is always
+    /// reserved for the overflow series itself.
+    pub(crate) fn attribute_set(&self, size: usize, attrs: AttributeSet) -> AttributeSet {
+        if self.max_cardinality == 0 || size < self.max_cardinality - 1 {
+            attrs
+        } else {
+            STREAM_OVERFLOW_ATTRIBUTE_SET.clone()
+        }
+    }
+}
+
+/// Default number of exemplars retained per data point when a stream does not
+/// otherwise configure a reservoir size.
+///
+/// Mirrors the Go SDK's default of one exemplar per CPU, which keeps memory
+/// bounded while still giving multi-core workloads a representative sample.
+fn default_reservoir_size() -> usize {
+    std::thread::available_parallelism()
+        .map(|n| n.get())
+        .unwrap_or(1)
+}
+
+/// A fixed-size exemplar reservoir using Algorithm R (reservoir sampling).
+///
+/// Every measurement is offered to the reservoir via [`Reservoir::offer`]. The
+/// first `capacity` measurements are always kept; afterwards the n-th
+/// measurement replaces a uniformly random existing slot with probability
+/// `capacity / n`, so the retained exemplars stay a uniform random sample of
+/// everything seen since the last resetting [`Reservoir::collect`].
+struct Reservoir<T> {
+    exemplars: Vec<Exemplar<T>>,
+    capacity: usize,
+    count: u64,
+}
+
+impl<T> Reservoir<T> {
+    fn new(capacity: usize) -> Self {
+        Reservoir {
+            exemplars: Vec::with_capacity(capacity),
+            capacity,
+            count: 0,
+        }
+    }
+
+    /// Offers a measurement to the reservoir, capturing the current span's
+    /// trace context (if sampled) and any attributes dropped by the view.
+    fn offer(&mut self, value: T, time: SystemTime, dropped_attributes: &[KeyValue]) {
+        self.count += 1;
+        if self.capacity == 0 {
+            return;
+        }
+
+        let slot = if self.exemplars.len() < self.capacity {
+            Some(self.exemplars.len())
+        } else {
+            let r = rand::random::<u64>() % self.count;
+            (r < self.capacity as u64).then_some(r as usize)
+        };
+
+        let Some(slot) = slot else {
+            return;
+        };
+
+        let cx = Context::current();
+        let span_context = cx.span().span_context().clone();
+        let (trace_id, span_id) = if span_context.is_valid() && span_context.is_sampled() {
+            (
+                span_context.trace_id().to_bytes(),
+                span_context.span_id().to_bytes(),
+            )
+        } else {
+            ([0u8; 16], [0u8; 8])
+        };
+
+        let exemplar = Exemplar {
+            filtered_attributes: dropped_attributes.to_vec(),
+            time,
+            value,
+            span_id,
+            trace_id,
+        };
+
+        if slot == self.exemplars.len() {
+            self.exemplars.push(exemplar);
+        } else {
+            self.exemplars[slot] = exemplar;
+        }
+    }
+
+    /// Returns a copy of the retained exemplars. With `reset: true` it also
+    /// clears them and resets the sample count so the next collection cycle
+    /// starts sampling from scratch (delta temporality). Cumulative
+    /// collection passes `reset: false`, which leaves the reservoir as it is
+    /// so the running sample keeps growing across cycles.
+    fn collect(&mut self, reset: bool) -> Vec<Exemplar<T>>
+    where
+        T: Clone,
+    {
+        let out = self.exemplars.clone();
+        if reset {
+            self.exemplars.clear();
+            self.count = 0;
+        }
+        out
+    }
+}
+
+/// A running sum paired with the exemplar reservoir sampled alongside it.
+///
+/// The sum itself is held in `T::AtomicTracker` so that the common case of
+/// updating an existing attribute set (a `fetch_add`) never needs to take a
+/// write lock on the surrounding map; only the reservoir keeps its own small
+/// `Mutex`, which every measurement still locks to offer itself for sampling.
+struct Tracker<T: AtomicallyUpdate<T>> {
+    value: T::AtomicTracker,
+    reservoir: Mutex<Reservoir<T>>,
+    /// Nanoseconds since the Unix epoch of the last `measure` that touched
+    /// this attribute set, used to evict stale streams from cumulative
+    /// aggregations. Stored as an atomic so stamping it doesn't need the
+    /// write lock any more than updating `value` does.
+    last_update: AtomicU64,
+}
+
+impl<T: Number<T> + AtomicallyUpdate<T>> Tracker<T> {
+    fn new(value: T, capacity: usize, time: SystemTime) -> Self {
+        Tracker {
+            value: T::new_atomic_tracker(value),
+            reservoir: Mutex::new(Reservoir::new(capacity)),
+            last_update: AtomicU64::new(nanos_since_epoch(time)),
+        }
+    }
+
+    fn touch(&self, time: SystemTime) {
+        self.last_update.store(nanos_since_epoch(time), Ordering::Relaxed);
+    }
+
+    fn is_stale(&self, ttl: Duration, now: SystemTime) -> bool {
+        let last_update = self.last_update.load(Ordering::Relaxed);
+        nanos_since_epoch(now).saturating_sub(last_update) > ttl.as_nanos() as u64
+    }
+}
+
+fn nanos_since_epoch(time: SystemTime) -> u64 {
+    time.duration_since(SystemTime::UNIX_EPOCH)
+        .map(|d| d.as_nanos() as u64)
+        .unwrap_or(0)
+}
 
 /// The storage for sums.
-#[derive(Default)]
-struct ValueMap<T: Number<T>> {
-    values: Mutex<HashMap<AttributeSet, T>>,
+///
+/// Reads (adding to an already-seen attribute set, which is the overwhelming
+/// majority of `measure` calls) only need a shared read lock, since the
+/// per-stream value is an atomic. The write lock is only taken the first
+/// time a brand-new attribute set is observed, to insert its tracker.
+struct ValueMap<T: Number<T> + AtomicallyUpdate<T>> {
+    values: RwLock<HashMap<AttributeSet, Arc<Tracker<T>>>>,
+    reservoir_capacity: usize,
+    limiter: Limiter,
+    /// Opt-in staleness policy: cumulative collection drops (and forgets the
+    /// value of) any attribute set whose tracker hasn't been touched within
+    /// this long. `None` keeps the previous unbounded-growth behavior.
+    ttl: Option<Duration>,
+}
+
+impl<T: Number<T> + AtomicallyUpdate<T>> Default for ValueMap<T> {
+    fn default() -> Self {
+        ValueMap::new(0, None, None)
+    }
 }
 
-impl<T: Number<T>> ValueMap<T> {
-    fn new() -> Self {
+impl<T: Number<T> + AtomicallyUpdate<T>> ValueMap<T> {
+    /// `reservoir_size`, if set, overrides the default per-data-point
+    /// exemplar reservoir capacity (e.g. from a view/stream config); `None`
+    /// falls back to [`default_reservoir_size`].
+    fn new(max_cardinality: usize, ttl: Option<Duration>, reservoir_size: Option<usize>) -> Self {
         ValueMap {
-            values: Mutex::new(HashMap::new()),
+            values: RwLock::new(HashMap::new()),
+            reservoir_capacity: reservoir_size.unwrap_or_else(default_reservoir_size),
+            limiter: Limiter::new(max_cardinality),
+            ttl,
         }
     }
 }
 
-impl<T: Number<T>> ValueMap<T> {
-    fn measure(&self, measurement: T, attrs: AttributeSet) {
-        if let Ok(mut values) = self.values.lock() {
-            let size = values.len();
-            match values.entry(attrs) {
-                Entry::Occupied(mut occupied_entry) => {
-                    let sum = occupied_entry.get_mut();
-                    *sum += measurement;
+impl<T: Number<T> + AtomicallyUpdate<T>> ValueMap<T> {
+    fn measure(&self, measurement: T, attrs: AttributeSet, dropped_attributes: &[KeyValue]) {
+        self.measure_batch(&[(measurement, attrs)], dropped_attributes);
+    }
+
+    fn record(
+        &self,
+        tracker: &Tracker<T>,
+        measurement: T,
+        time: SystemTime,
+        dropped_attributes: &[KeyValue],
+    ) {
+        tracker.value.add(measurement);
+        tracker.touch(time);
+        if let Ok(mut reservoir) = tracker.reservoir.lock() {
+            reservoir.offer(measurement, time, dropped_attributes);
+        }
+    }
+
+    /// Applies a burst of measurements taking the map's read lock once, and
+    /// its write lock at most once, instead of one lock/unlock cycle per
+    /// measurement. Most callers hit the fast path below (every attribute
+    /// set already has a tracker) and need only the read lock; any new
+    /// attribute sets in the batch are inserted together under one write
+    /// lock, taken after the read lock is released, with the cardinality
+    /// limit applied across the whole batch.
+    /// `dropped_attributes` are the measurement attributes the view dropped
+    /// for this stream; they're recorded on every exemplar taken from this
+    /// batch.
+    fn measure_batch(&self, measurements: &[(T, AttributeSet)], dropped_attributes: &[KeyValue]) {
+        let time = SystemTime::now();
+
+        let mut missing = Vec::new();
+        if let Ok(values) = self.values.read() {
+            for (i, (measurement, attrs)) in measurements.iter().enumerate() {
+                match values.get(attrs) {
+                    Some(tracker) => self.record(tracker, *measurement, time, dropped_attributes),
+                    None => missing.push(i),
                 }
-                Entry::Vacant(vacant_entry) => {
-                    if is_under_cardinality_limit(size) {
-                        vacant_entry.insert(measurement);
-                    } else {
-                        values
-                            .entry(STREAM_OVERFLOW_ATTRIBUTE_SET.clone())
-                            .and_modify(|val| *val += measurement)
-                            .or_insert(measurement);
-                        global::handle_error(MetricsError::Other("Warning: Maximum data points for metric stream exceeded. Entry added to overflow.".into()));
-                    }
+            }
+        }
+
+        if missing.is_empty() {
+            return;
+        }
+
+        if let Ok(mut values) = self.values.write() {
+            for i in missing {
+                let (measurement, attrs) = &measurements[i];
+                // An earlier "missing" index in this same batch may have
+                // already inserted this exact attrs (duplicate labels in a
+                // flushed buffer): re-check under the write lock so it takes
+                // the fast path here instead of being re-evaluated against
+                // the cardinality limit and possibly redirected to overflow.
+                if let Some(tracker) = values.get(attrs) {
+                    self.record(tracker, *measurement, time, dropped_attributes);
+                    continue;
+                }
+                let size = values.len();
+                let key = self.limiter.attribute_set(size, attrs.clone());
+                let is_overflow = *key == *STREAM_OVERFLOW_ATTRIBUTE_SET;
+                // The overflow series merges exemplars from many unrelated
+                // attribute sets, so there's no single original series to
+                // attribute a sampled exemplar to; keep it reservoir-free
+                // rather than sampling misleading exemplars for it.
+                let capacity = if is_overflow { 0 } else { self.reservoir_capacity };
+                let tracker = values
+                    .entry(key)
+                    .or_insert_with(|| Arc::new(Tracker::new(T::default(), capacity, time)));
+                self.record(tracker, *measurement, time, dropped_attributes);
+                if is_overflow {
+                    global::handle_error(MetricsError::Other("Warning: Maximum data points for metric stream exceeded. Entry added to overflow.".into()));
                 }
             }
         }
@@ -53,28 +296,54 @@ impl<T: Number<T>> ValueMap<T> {
 }
 
 /// Summarizes a set of measurements made as their arithmetic sum.
-pub(crate) struct Sum<T: Number<T>> {
+pub(crate) struct Sum<T: Number<T> + AtomicallyUpdate<T>> {
     value_map: ValueMap<T>,
     monotonic: bool,
     start: Mutex<SystemTime>,
 }
 
-impl<T: Number<T>> Sum<T> {
+impl<T: Number<T> + AtomicallyUpdate<T>> Sum<T> {
     /// Returns an aggregator that summarizes a set of measurements as their
     /// arithmetic sum.
     ///
     /// Each sum is scoped by attributes and the aggregation cycle the measurements
-    /// were made in.
-    pub(crate) fn new(monotonic: bool) -> Self {
+    /// were made in. `max_cardinality` caps the number of distinct attribute
+    /// sets tracked before overflowing into a shared series; `0` means
+    /// unlimited. `ttl`, if set, evicts attribute sets from cumulative
+    /// collection once they haven't been updated within that long.
+    /// `reservoir_size`, if set, overrides the default exemplar reservoir
+    /// capacity per attribute set.
+    pub(crate) fn new(
+        monotonic: bool,
+        max_cardinality: usize,
+        ttl: Option<Duration>,
+        reservoir_size: Option<usize>,
+    ) -> Self {
         Sum {
-            value_map: ValueMap::new(),
+            value_map: ValueMap::new(max_cardinality, ttl, reservoir_size),
             monotonic,
             start: Mutex::new(SystemTime::now()),
         }
     }
 
-    pub(crate) fn measure(&self, measurement: T, attrs: AttributeSet) {
-        self.value_map.measure(measurement, attrs)
+    pub(crate) fn measure(
+        &self,
+        measurement: T,
+        attrs: AttributeSet,
+        dropped_attributes: &[KeyValue],
+    ) {
+        self.value_map.measure(measurement, attrs, dropped_attributes)
+    }
+
+    /// Records a burst of measurements (e.g. a flushed thread-local buffer)
+    /// under one read lock, plus one write lock only if the batch holds new
+    /// attribute sets, instead of a lock cycle per measurement.
+    pub(crate) fn measure_batch(
+        &self,
+        measurements: &[(T, AttributeSet)],
+        dropped_attributes: &[KeyValue],
+    ) {
+        self.value_map.measure_batch(measurements, dropped_attributes)
     }
 
     pub(crate) fn delta(
@@ -97,7 +366,7 @@ impl<T: Number<T>> Sum<T> {
         s_data.temporality = Temporality::Delta;
         s_data.is_monotonic = self.monotonic;
 
-        let mut values = match self.value_map.values.lock() {
+        let mut values = match self.value_map.values.write() {
             Ok(v) => v,
             Err(_) => return (0, None),
         };
@@ -110,20 +379,26 @@ impl<T: Number<T>> Sum<T> {
         }
 
         let prev_start = self.start.lock().map(|start| *start).unwrap_or(t);
-        for (i, (attrs, value)) in values.drain().enumerate() {
+        for (i, (attrs, tracker)) in values.drain().enumerate() {
+            let value = tracker.value.get_and_reset_value();
+            let exemplars = tracker
+                .reservoir
+                .lock()
+                .map(|mut r| r.collect(true))
+                .unwrap_or_default();
             if let Some(dp) = s_data.data_points.get_mut(i) {
                 dp.attributes = attrs;
                 dp.start_time = Some(prev_start);
                 dp.time = Some(t);
                 dp.value = value;
-                dp.exemplars.clear()
+                dp.exemplars = exemplars;
             } else {
                 s_data.data_points.push(DataPoint {
                     attributes: attrs,
                     start_time: Some(prev_start),
                     time: Some(t),
                     value,
-                    exemplars: vec![],
+                    exemplars,
                 });
             }
         }
@@ -155,7 +430,18 @@ impl<T: Number<T>> Sum<T> {
         s_data.temporality = Temporality::Cumulative;
         s_data.is_monotonic = self.monotonic;
 
-        let values = match self.value_map.values.lock() {
+        // Attribute sets that haven't been updated within the configured TTL
+        // are forgotten here so cumulative collection doesn't grow
+        // unboundedly for streams that stopped reporting. This only takes
+        // the write lock when a TTL is actually configured, so the common
+        // (TTL-disabled) case stays on the cheaper read lock below.
+        if let Some(ttl) = self.value_map.ttl {
+            if let Ok(mut values) = self.value_map.values.write() {
+                values.retain(|_, tracker| !tracker.is_stale(ttl, t));
+            }
+        }
+
+        let values = match self.value_map.values.read() {
             Ok(v) => v,
             Err(_) => return (0, None),
         };
@@ -168,52 +454,115 @@ impl<T: Number<T>> Sum<T> {
         }
 
         let prev_start = self.start.lock().map(|start| *start).unwrap_or(t);
-        // TODO: This will use an unbounded amount of memory if there
-        // are unbounded number of attribute sets being aggregated. Attribute
-        // sets that become "stale" need to be forgotten so this will not
-        // overload the system.
-        for (i, (attrs, value)) in values.iter().enumerate() {
+        for (i, (attrs, tracker)) in values.iter().enumerate() {
+            let value = tracker.value.get_value();
+            let exemplars = tracker
+                .reservoir
+                .lock()
+                .map(|mut r| r.collect(false))
+                .unwrap_or_default();
             if let Some(dp) = s_data.data_points.get_mut(i) {
                 dp.attributes = attrs.clone();
                 dp.start_time = Some(prev_start);
                 dp.time = Some(t);
-                dp.value = *value;
-                dp.exemplars.clear()
+                dp.value = value;
+                dp.exemplars = exemplars;
             } else {
                 s_data.data_points.push(DataPoint {
                     attributes: attrs.clone(),
                     start_time: Some(prev_start),
                     time: Some(t),
-                    value: *value,
-                    exemplars: vec![],
+                    value,
+                    exemplars,
                 });
             }
         }
 
         (n, new_agg.map(|a| Box::new(a) as Box<_>))
     }
+
+    /// Returns a read-only snapshot of the current aggregation state without
+    /// advancing the delta collection cycle or touching `start`. Useful for
+    /// exposing live metric state (e.g. a debug endpoint) without
+    /// interfering with the periodic reader's own bookkeeping.
+    pub(crate) fn snapshot(&self) -> data::Sum<T> {
+        let t = SystemTime::now();
+        let prev_start = self.start.lock().map(|start| *start).unwrap_or(t);
+
+        let mut data_points = vec![];
+        if let Ok(values) = self.value_map.values.read() {
+            data_points.reserve(values.len());
+            for (attrs, tracker) in values.iter() {
+                let exemplars = tracker
+                    .reservoir
+                    .lock()
+                    .map(|mut r| r.collect(false))
+                    .unwrap_or_default();
+                data_points.push(DataPoint {
+                    attributes: attrs.clone(),
+                    start_time: Some(prev_start),
+                    time: Some(t),
+                    value: tracker.value.get_value(),
+                    exemplars,
+                });
+            }
+        }
+
+        data::Sum {
+            data_points,
+            temporality: Temporality::Cumulative,
+            is_monotonic: self.monotonic,
+        }
+    }
 }
 
 /// Summarizes a set of pre-computed sums as their arithmetic sum.
-pub(crate) struct PrecomputedSum<T: Number<T>> {
+pub(crate) struct PrecomputedSum<T: Number<T> + AtomicallyUpdate<T>> {
     value_map: ValueMap<T>,
     monotonic: bool,
     start: Mutex<SystemTime>,
     reported: Mutex<HashMap<AttributeSet, T>>,
 }
 
-impl<T: Number<T>> PrecomputedSum<T> {
-    pub(crate) fn new(monotonic: bool) -> Self {
+impl<T: Number<T> + AtomicallyUpdate<T>> PrecomputedSum<T> {
+    /// `max_cardinality` caps the number of distinct attribute sets tracked
+    /// before overflowing into a shared series; `0` means unlimited. `ttl`,
+    /// if set, evicts attribute sets (and their reported values) from
+    /// cumulative collection once they haven't been updated within that
+    /// long. `reservoir_size`, if set, overrides the default exemplar
+    /// reservoir capacity per attribute set.
+    pub(crate) fn new(
+        monotonic: bool,
+        max_cardinality: usize,
+        ttl: Option<Duration>,
+        reservoir_size: Option<usize>,
+    ) -> Self {
         PrecomputedSum {
-            value_map: ValueMap::new(),
+            value_map: ValueMap::new(max_cardinality, ttl, reservoir_size),
             monotonic,
             start: Mutex::new(SystemTime::now()),
             reported: Mutex::new(Default::default()),
         }
     }
 
-    pub(crate) fn measure(&self, measurement: T, attrs: AttributeSet) {
-        self.value_map.measure(measurement, attrs)
+    pub(crate) fn measure(
+        &self,
+        measurement: T,
+        attrs: AttributeSet,
+        dropped_attributes: &[KeyValue],
+    ) {
+        self.value_map.measure(measurement, attrs, dropped_attributes)
+    }
+
+    /// Records a burst of measurements (e.g. a flushed thread-local buffer)
+    /// under one read lock, plus one write lock only if the batch holds new
+    /// attribute sets, instead of a lock cycle per measurement.
+    pub(crate) fn measure_batch(
+        &self,
+        measurements: &[(T, AttributeSet)],
+        dropped_attributes: &[KeyValue],
+    ) {
+        self.value_map.measure_batch(measurements, dropped_attributes)
     }
 
     pub(crate) fn delta(
@@ -235,7 +584,7 @@ impl<T: Number<T>> PrecomputedSum<T> {
         };
         let s_data = s_data.unwrap_or_else(|| new_agg.as_mut().expect("present if s_data is none"));
 
-        let mut values = match self.value_map.values.lock() {
+        let mut values = match self.value_map.values.write() {
             Ok(v) => v,
             Err(_) => return (0, None),
         };
@@ -253,24 +602,30 @@ impl<T: Number<T>> PrecomputedSum<T> {
         };
 
         let default = T::default();
-        for (i, (attrs, value)) in values.drain().enumerate() {
+        for (i, (attrs, tracker)) in values.drain().enumerate() {
+            let value = tracker.value.get_and_reset_value();
             let delta = value - *reported.get(&attrs).unwrap_or(&default);
             if delta != default {
                 new_reported.insert(attrs.clone(), value);
             }
+            let exemplars = tracker
+                .reservoir
+                .lock()
+                .map(|mut r| r.collect(true))
+                .unwrap_or_default();
             if let Some(dp) = s_data.data_points.get_mut(i) {
                 dp.attributes = attrs.clone();
                 dp.start_time = Some(prev_start);
                 dp.time = Some(t);
                 dp.value = delta;
-                dp.exemplars.clear();
+                dp.exemplars = exemplars;
             } else {
                 s_data.data_points.push(DataPoint {
                     attributes: attrs.clone(),
                     start_time: Some(prev_start),
                     time: Some(t),
                     value: delta,
-                    exemplars: vec![],
+                    exemplars,
                 });
             }
         }
@@ -305,7 +660,19 @@ impl<T: Number<T>> PrecomputedSum<T> {
         };
         let s_data = s_data.unwrap_or_else(|| new_agg.as_mut().expect("present if s_data is none"));
 
-        let values = match self.value_map.values.lock() {
+        // Attribute sets that haven't been updated within the configured TTL
+        // are forgotten here, along with their last-reported value, so
+        // cumulative collection doesn't grow unboundedly for streams that
+        // stopped reporting. This only takes the write lock when a TTL is
+        // actually configured, so the common (TTL-disabled) case stays on
+        // the cheaper read lock below.
+        if let Some(ttl) = self.value_map.ttl {
+            if let Ok(mut values) = self.value_map.values.write() {
+                values.retain(|_, tracker| !tracker.is_stale(ttl, t));
+            }
+        }
+
+        let values = match self.value_map.values.read() {
             Ok(v) => v,
             Err(_) => return (0, None),
         };
@@ -323,24 +690,30 @@ impl<T: Number<T>> PrecomputedSum<T> {
         };
 
         let default = T::default();
-        for (i, (attrs, value)) in values.iter().enumerate() {
-            let delta = *value - *reported.get(attrs).unwrap_or(&default);
+        for (i, (attrs, tracker)) in values.iter().enumerate() {
+            let value = tracker.value.get_value();
+            let delta = value - *reported.get(attrs).unwrap_or(&default);
             if delta != default {
-                new_reported.insert(attrs.clone(), *value);
+                new_reported.insert(attrs.clone(), value);
             }
+            let exemplars = tracker
+                .reservoir
+                .lock()
+                .map(|mut r| r.collect(false))
+                .unwrap_or_default();
             if let Some(dp) = s_data.data_points.get_mut(i) {
                 dp.attributes = attrs.clone();
                 dp.start_time = Some(prev_start);
                 dp.time = Some(t);
                 dp.value = delta;
-                dp.exemplars.clear();
+                dp.exemplars = exemplars;
             } else {
                 s_data.data_points.push(DataPoint {
                     attributes: attrs.clone(),
                     start_time: Some(prev_start),
                     time: Some(t),
                     value: delta,
-                    exemplars: vec![],
+                    exemplars,
                 });
             }
         }
@@ -350,4 +723,283 @@ impl<T: Number<T>> PrecomputedSum<T> {
 
         (n, new_agg.map(|a| Box::new(a) as Box<_>))
     }
+
+    /// Returns a read-only snapshot of the current aggregation state without
+    /// advancing the delta collection cycle or touching `start`/`reported`.
+    /// Useful for exposing live metric state (e.g. a debug endpoint) without
+    /// interfering with the periodic reader's own bookkeeping.
+    pub(crate) fn snapshot(&self) -> data::Sum<T> {
+        let t = SystemTime::now();
+        let prev_start = self.start.lock().map(|start| *start).unwrap_or(t);
+        let default = T::default();
+
+        let mut data_points = vec![];
+        if let (Ok(values), Ok(reported)) = (self.value_map.values.read(), self.reported.lock()) {
+            data_points.reserve(values.len());
+            for (attrs, tracker) in values.iter() {
+                // Mirrors `cumulative()`'s delta-vs-reported-baseline
+                // contract, without mutating `reported`, so a snapshot reads
+                // the same value the periodic reader would report.
+                let value = tracker.value.get_value() - *reported.get(attrs).unwrap_or(&default);
+                let exemplars = tracker
+                    .reservoir
+                    .lock()
+                    .map(|mut r| r.collect(false))
+                    .unwrap_or_default();
+                data_points.push(DataPoint {
+                    attributes: attrs.clone(),
+                    start_time: Some(prev_start),
+                    time: Some(t),
+                    value,
+                    exemplars,
+                });
+            }
+        }
+
+        data::Sum {
+            data_points,
+            temporality: Temporality::Cumulative,
+            is_monotonic: self.monotonic,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn attrs(kv: &[(&'static str, &'static str)]) -> AttributeSet {
+        let kvs: Vec<KeyValue> = kv
+            .iter()
+            .map(|(k, v)| KeyValue::new(*k, *v))
+            .collect();
+        AttributeSet::from(&kvs[..])
+    }
+
+    #[test]
+    fn reservoir_keeps_all_measurements_up_to_capacity() {
+        let mut reservoir = Reservoir::<u64>::new(3);
+        for i in 0..3 {
+            reservoir.offer(i, SystemTime::now(), &[]);
+        }
+        let exemplars = reservoir.collect(false);
+        assert_eq!(exemplars.len(), 3);
+        let mut values: Vec<u64> = exemplars.iter().map(|e| e.value).collect();
+        values.sort();
+        assert_eq!(values, vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn reservoir_caps_at_capacity_once_full() {
+        let mut reservoir = Reservoir::<u64>::new(3);
+        for i in 0..100 {
+            reservoir.offer(i, SystemTime::now(), &[]);
+        }
+        // Algorithm R never grows the sample past its configured capacity,
+        // no matter how many measurements are offered.
+        assert_eq!(reservoir.collect(false).len(), 3);
+    }
+
+    #[test]
+    fn reservoir_with_zero_capacity_retains_nothing() {
+        let mut reservoir = Reservoir::<u64>::new(0);
+        reservoir.offer(1, SystemTime::now(), &[]);
+        assert!(reservoir.collect(false).is_empty());
+    }
+
+    #[test]
+    fn reservoir_collect_reset_starts_sampling_over() {
+        let mut reservoir = Reservoir::<u64>::new(2);
+        reservoir.offer(1, SystemTime::now(), &[]);
+        reservoir.offer(2, SystemTime::now(), &[]);
+        assert_eq!(reservoir.collect(true).len(), 2);
+        assert_eq!(reservoir.count, 0);
+        assert!(reservoir.exemplars.is_empty());
+    }
+
+    #[test]
+    fn dropped_attributes_are_plumbed_into_exemplars() {
+        let value_map = ValueMap::<u64>::new(0, None, Some(1));
+        let dropped = [KeyValue::new("dropped", "yes")];
+        value_map.measure(5, attrs(&[("a", "1")]), &dropped);
+
+        let values = value_map.values.read().unwrap();
+        let tracker = values.get(&attrs(&[("a", "1")])).unwrap();
+        let exemplars = tracker.reservoir.lock().unwrap().collect(false);
+        assert_eq!(exemplars.len(), 1);
+        assert_eq!(exemplars[0].filtered_attributes, dropped.to_vec());
+    }
+
+    #[test]
+    fn reservoir_size_is_configurable() {
+        let value_map = ValueMap::<u64>::new(0, None, Some(7));
+        assert_eq!(value_map.reservoir_capacity, 7);
+
+        let default_map = ValueMap::<u64>::new(0, None, None);
+        assert_eq!(default_map.reservoir_capacity, default_reservoir_size());
+    }
+
+    #[test]
+    fn limiter_unlimited_when_max_cardinality_is_zero() {
+        let limiter = Limiter::new(0);
+        let a = attrs(&[("a", "1")]);
+        assert_eq!(limiter.attribute_set(1_000_000, a.clone()), a);
+    }
+
+    #[test]
+    fn limiter_passes_through_below_the_limit() {
+        let limiter = Limiter::new(3);
+        let a = attrs(&[("a", "1")]);
+        // One slot is reserved for the overflow series itself, so only
+        // `max_cardinality - 1` real streams are let through.
+        assert_eq!(limiter.attribute_set(0, a.clone()), a);
+        assert_eq!(limiter.attribute_set(1, a.clone()), a);
+    }
+
+    #[test]
+    fn limiter_overflows_at_the_boundary() {
+        let limiter = Limiter::new(3);
+        let a = attrs(&[("a", "1")]);
+        assert_eq!(
+            limiter.attribute_set(2, a),
+            *STREAM_OVERFLOW_ATTRIBUTE_SET
+        );
+    }
+
+    #[test]
+    fn value_map_redirects_new_attrs_to_overflow_once_at_capacity() {
+        let value_map = ValueMap::<u64>::new(2, None, Some(1));
+        value_map.measure(1, attrs(&[("a", "1")]), &[]);
+        value_map.measure(1, attrs(&[("b", "2")]), &[]);
+
+        let values = value_map.values.read().unwrap();
+        assert!(values.contains_key(&attrs(&[("a", "1")])));
+        assert!(values.contains_key(&*STREAM_OVERFLOW_ATTRIBUTE_SET));
+        assert!(!values.contains_key(&attrs(&[("b", "2")])));
+    }
+
+    #[test]
+    fn existing_attrs_take_the_atomic_fast_path_under_a_read_lock() {
+        let value_map = ValueMap::<u64>::new(0, None, Some(1));
+        let a = attrs(&[("a", "1")]);
+        value_map.measure(1, a.clone(), &[]);
+
+        // Once a tracker exists, repeat measurements for the same attrs
+        // should update it through the held read lock (a write lock would
+        // deadlock here since we're still holding the read guard).
+        let values = value_map.values.read().unwrap();
+        let tracker = values.get(&a).unwrap();
+        for _ in 0..9 {
+            value_map.measure(1, a.clone(), &[]);
+        }
+        assert_eq!(tracker.value.get_value(), 10);
+    }
+
+    #[test]
+    fn measure_accumulates_across_many_calls() {
+        let value_map = ValueMap::<u64>::new(0, None, Some(1));
+        let a = attrs(&[("a", "1")]);
+        for _ in 0..100 {
+            value_map.measure(1, a.clone(), &[]);
+        }
+        let values = value_map.values.read().unwrap();
+        assert_eq!(values.get(&a).unwrap().value.get_value(), 100);
+    }
+
+    #[test]
+    fn measure_batch_merges_duplicate_attrs_in_one_call() {
+        let value_map = ValueMap::<u64>::new(0, None, Some(1));
+        let a = attrs(&[("a", "1")]);
+        value_map.measure_batch(&[(1, a.clone()), (2, a.clone()), (3, a.clone())], &[]);
+
+        let values = value_map.values.read().unwrap();
+        assert_eq!(values.len(), 1);
+        assert_eq!(values.get(&a).unwrap().value.get_value(), 6);
+    }
+
+    #[test]
+    fn measure_batch_does_not_split_duplicate_attrs_into_overflow() {
+        // Cardinality limit of 2 means only one real stream plus the
+        // overflow series fit. A batch that repeats the *same* new attrs
+        // twice must not count the second occurrence as a second distinct
+        // stream and redirect it to overflow.
+        let value_map = ValueMap::<u64>::new(2, None, Some(1));
+        let a = attrs(&[("a", "1")]);
+        value_map.measure_batch(&[(1, a.clone()), (1, a.clone())], &[]);
+
+        let values = value_map.values.read().unwrap();
+        assert_eq!(values.len(), 1);
+        assert!(values.contains_key(&a));
+        assert!(!values.contains_key(&*STREAM_OVERFLOW_ATTRIBUTE_SET));
+        assert_eq!(values.get(&a).unwrap().value.get_value(), 2);
+    }
+
+    #[test]
+    fn measure_batch_mixes_existing_and_new_attrs() {
+        let value_map = ValueMap::<u64>::new(0, None, Some(1));
+        let a = attrs(&[("a", "1")]);
+        let b = attrs(&[("b", "2")]);
+        value_map.measure(1, a.clone(), &[]);
+        value_map.measure_batch(&[(1, a.clone()), (1, b.clone())], &[]);
+
+        let values = value_map.values.read().unwrap();
+        assert_eq!(values.get(&a).unwrap().value.get_value(), 2);
+        assert_eq!(values.get(&b).unwrap().value.get_value(), 1);
+    }
+
+    #[test]
+    fn cumulative_evicts_stale_attrs_once_ttl_elapses() {
+        let sum = Sum::<u64>::new(true, 0, Some(Duration::from_millis(1)), Some(1));
+        let a = attrs(&[("a", "1")]);
+        sum.measure(1, a.clone(), &[]);
+
+        // Back-date the tracker's last-update so it's already past the TTL,
+        // without needing to actually sleep in the test.
+        {
+            let values = sum.value_map.values.read().unwrap();
+            let tracker = values.get(&a).unwrap();
+            tracker.last_update.store(0, Ordering::Relaxed);
+        }
+
+        let (n, agg) = sum.cumulative(None);
+        assert_eq!(n, 0);
+        let agg = agg.unwrap();
+        let data = agg.as_any().downcast_ref::<data::Sum<u64>>().unwrap();
+        assert!(data.data_points.is_empty());
+        assert!(!sum.value_map.values.read().unwrap().contains_key(&a));
+    }
+
+    #[test]
+    fn cumulative_keeps_fresh_attrs_within_ttl() {
+        let sum = Sum::<u64>::new(true, 0, Some(Duration::from_secs(3600)), Some(1));
+        let a = attrs(&[("a", "1")]);
+        sum.measure(1, a.clone(), &[]);
+
+        let (n, _) = sum.cumulative(None);
+        assert_eq!(n, 1);
+        assert!(sum.value_map.values.read().unwrap().contains_key(&a));
+    }
+
+    #[test]
+    fn precomputed_sum_snapshot_matches_cumulative() {
+        let sum = PrecomputedSum::<u64>::new(true, 0, None, Some(1));
+        let a = attrs(&[("a", "1")]);
+        sum.measure(10, a.clone(), &[]);
+
+        // A plain snapshot (no side effects) should read the same baselined
+        // value that the periodic reader's cumulative() would report, and
+        // calling it repeatedly must not move the `reported` baseline.
+        let snapshot = sum.snapshot();
+        assert_eq!(snapshot.data_points.len(), 1);
+        assert_eq!(snapshot.data_points[0].value, 10);
+
+        let snapshot_again = sum.snapshot();
+        assert_eq!(snapshot_again.data_points[0].value, 10);
+
+        let (_, agg) = sum.cumulative(None);
+        let agg = agg.unwrap();
+        let cumulative = agg.as_any().downcast_ref::<data::Sum<u64>>().unwrap();
+        assert_eq!(cumulative.data_points.len(), 1);
+        assert_eq!(cumulative.data_points[0].value, 10);
+    }
 }